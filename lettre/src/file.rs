@@ -0,0 +1,54 @@
+//! The file transport writes the emails to the given directory, as a single
+//! JSON file describing the envelope and message content. It can be useful
+//! for testing purposes.
+
+use serde_json;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use {Envelope, SendableEmail, Transport};
+
+/// Writes the content and the envelope information to a file
+#[derive(Debug, Clone)]
+pub struct FileTransport {
+    path: PathBuf,
+}
+
+impl FileTransport {
+    /// Creates a new transport to the given directory
+    pub fn new<P: AsRef<Path>>(path: P) -> FileTransport {
+        FileTransport {
+            path: PathBuf::from(path.as_ref()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SerializableEmail<'a> {
+    envelope: &'a Envelope,
+    message_id: &'a str,
+    message: String,
+}
+
+impl<'a> Transport<'a> for FileTransport {
+    type Result = Result<(), ::std::io::Error>;
+
+    fn send(&mut self, email: SendableEmail) -> Self::Result {
+        let message_id = email.message_id().to_string();
+        let envelope = email.envelope().clone();
+        let message = email.message_to_string()?;
+
+        let email = SerializableEmail {
+            envelope: &envelope,
+            message_id: &message_id,
+            message,
+        };
+
+        let file = self.path.join(format!("{}.json", message_id));
+        let mut f = File::create(file)?;
+        let serialized = serde_json::to_string(&email)
+            .map_err(|err| ::std::io::Error::new(::std::io::ErrorKind::Other, err))?;
+        f.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+}
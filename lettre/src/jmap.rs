@@ -0,0 +1,232 @@
+//! The JMAP transport submits mail via [JMAP](https://tools.ietf.org/html/rfc8620)
+//! (RFC 8620/8621) instead of SMTP, so callers can target modern JMAP
+//! providers directly without an SMTP submission server.
+
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use reqwest::Client as HttpClient;
+use serde_json::{self, Value};
+use std::error::Error as StdError;
+use std::fmt::{self, Display, Formatter};
+use std::io::Read;
+use {SendableEmail, Transport};
+
+/// Errors returned by the JMAP transport
+#[derive(Debug)]
+pub enum Error {
+    /// The HTTP request to the JMAP server failed
+    Http(String),
+    /// The JMAP session, upload or method response could not be parsed
+    InvalidResponse(String),
+    /// A JMAP method call returned a `method-level` or submission error
+    Method(String),
+    /// An I/O error occurred while reading the message body
+    Io(::std::io::Error),
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Http(_) => "the JMAP HTTP request failed",
+            Error::InvalidResponse(_) => "could not parse the JMAP server response",
+            Error::Method(_) => "the JMAP server rejected the submission",
+            Error::Io(_) => "an I/O error occurred",
+        }
+    }
+
+    fn cause(&self) -> Option<&StdError> {
+        match *self {
+            Error::Io(ref err) => Some(&*err),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match *self {
+            Error::Http(ref s) | Error::InvalidResponse(ref s) | Error::Method(ref s) => {
+                fmt.write_str(s)
+            }
+            Error::Io(ref err) => Display::fmt(err, fmt),
+        }
+    }
+}
+
+impl From<::std::io::Error> for Error {
+    fn from(err: ::std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<::reqwest::Error> for Error {
+    fn from(err: ::reqwest::Error) -> Error {
+        Error::Http(err.to_string())
+    }
+}
+
+/// JMAP result type
+pub type JmapResult = Result<(), Error>;
+
+/// Submits mail through a JMAP server's `EmailSubmission/set` method
+///
+/// Authenticates with a bearer token, uploads the raw message bytes through
+/// the JMAP blob-upload endpoint, then asks the server to submit that blob
+/// using the envelope's `reverse_path` as the mail-from and `forward_path`
+/// entries as rcpt-to.
+pub struct JmapTransport {
+    session_url: String,
+    bearer_token: String,
+    account_id: String,
+    http: HttpClient,
+}
+
+impl JmapTransport {
+    /// Creates a new transport against the given JMAP session endpoint
+    pub fn new(session_url: &str, bearer_token: &str, account_id: &str) -> JmapTransport {
+        JmapTransport {
+            session_url: session_url.to_string(),
+            bearer_token: bearer_token.to_string(),
+            account_id: account_id.to_string(),
+            http: HttpClient::new(),
+        }
+    }
+
+    fn upload_blob(&self, upload_url: &str, message: &[u8]) -> Result<String, Error> {
+        let mut response = self
+            .http
+            .post(upload_url)
+            .header(AUTHORIZATION, format!("Bearer {}", self.bearer_token))
+            .header(CONTENT_TYPE, "message/rfc822")
+            .body(message.to_vec())
+            .send()?
+            .error_for_status()?;
+
+        let body: Value = response.json()?;
+        body.get("blobId")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| {
+                Error::InvalidResponse("upload response did not contain a \"blobId\"".to_string())
+            })
+    }
+
+    fn submit(&self, blob_id: &str, mail_from: &str, rcpt_to: &[String]) -> Result<(), Error> {
+        let envelope = json!({
+            "mailFrom": { "email": mail_from },
+            "rcptTo": rcpt_to.iter().map(|addr| json!({ "email": addr })).collect::<Vec<_>>(),
+        });
+
+        let request = json!({
+            "using": ["urn:ietf:params:jmap:core", "urn:ietf:params:jmap:submission"],
+            "methodCalls": [[
+                "EmailSubmission/set",
+                {
+                    "accountId": self.account_id,
+                    "create": {
+                        "send": {
+                            "emailId": format!("#{}", blob_id),
+                            "envelope": envelope,
+                        }
+                    }
+                },
+                "0"
+            ]],
+        });
+
+        let response = self.call(&request)?;
+        check_submission_errors(&response)
+    }
+
+    fn call(&self, request: &Value) -> Result<Value, Error> {
+        let mut response = self
+            .http
+            .post(&self.session_url)
+            .header(AUTHORIZATION, format!("Bearer {}", self.bearer_token))
+            .json(request)
+            .send()?
+            .error_for_status()?;
+        response.json().map_err(Error::from)
+    }
+}
+
+fn check_submission_errors(response: &Value) -> Result<(), Error> {
+    if response.pointer("/methodResponses/0/0").and_then(Value::as_str) == Some("error") {
+        let err = response
+            .pointer("/methodResponses/0/1")
+            .cloned()
+            .unwrap_or(Value::Null);
+        return Err(Error::Method(err.to_string()));
+    }
+    let created = response
+        .pointer("/methodResponses/0/1/created/send")
+        .is_some();
+    if created {
+        return Ok(());
+    }
+    let not_created = response.pointer("/methodResponses/0/1/notCreated/send");
+    match not_created {
+        Some(err) => Err(Error::Method(err.to_string())),
+        None => Err(Error::InvalidResponse(
+            "no EmailSubmission/set result for \"send\"".to_string(),
+        )),
+    }
+}
+
+impl<'a> Transport<'a> for JmapTransport {
+    type Result = JmapResult;
+
+    fn send(&mut self, email: SendableEmail) -> Self::Result {
+        let envelope = email.envelope().clone();
+        let mail_from = envelope.from().map(|a| a.to_string()).unwrap_or_default();
+        let rcpt_to: Vec<String> = envelope.to().iter().map(|a| a.to_string()).collect();
+
+        let mut message = email.message();
+        let mut bytes = Vec::new();
+        message.read_to_end(&mut bytes)?;
+
+        let upload_url = format!("{}/upload/{}/", self.session_url, self.account_id);
+        let blob_id = self.upload_blob(&upload_url, &bytes)?;
+        self.submit(&blob_id, &mail_from, &rcpt_to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_submission_errors_accepts_a_created_send() {
+        let response = json!({
+            "methodResponses": [
+                ["EmailSubmission/set", {"created": {"send": {"id": "abc"}}}, "0"]
+            ]
+        });
+        assert!(check_submission_errors(&response).is_ok());
+    }
+
+    #[test]
+    fn check_submission_errors_surfaces_a_not_created_send() {
+        let response = json!({
+            "methodResponses": [
+                ["EmailSubmission/set", {"notCreated": {"send": {"type": "invalidProperties"}}}, "0"]
+            ]
+        });
+        match check_submission_errors(&response) {
+            Err(Error::Method(_)) => {}
+            other => panic!("expected Method error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn check_submission_errors_rejects_a_response_missing_send() {
+        let response = json!({
+            "methodResponses": [
+                ["EmailSubmission/set", {"created": {}}, "0"]
+            ]
+        });
+        match check_submission_errors(&response) {
+            Err(Error::InvalidResponse(_)) => {}
+            other => panic!("expected InvalidResponse error, got {:?}", other),
+        }
+    }
+}
@@ -6,15 +6,17 @@
 #![doc(html_root_url = "https://docs.rs/lettre/0.9.0")]
 #![deny(missing_copy_implementations, trivial_casts, trivial_numeric_casts, unsafe_code,
         unstable_features, unused_import_braces, unused_qualifications)]
-#[cfg(feature = "smtp-transport")]
+#[cfg(any(feature = "smtp-transport", feature = "async-smtp-transport"))]
 extern crate base64;
 #[cfg(feature = "smtp-transport")]
 extern crate bufstream;
-#[cfg(feature = "smtp-transport")]
+#[cfg(feature = "async-smtp-transport")]
+extern crate futures;
+#[cfg(any(feature = "smtp-transport", feature = "maildir-transport"))]
 extern crate hostname;
 #[macro_use]
 extern crate log;
-#[cfg(feature = "smtp-transport")]
+#[cfg(any(feature = "smtp-transport", feature = "async-smtp-transport"))]
 extern crate native_tls;
 #[cfg(feature = "smtp-transport")]
 #[macro_use]
@@ -24,11 +26,28 @@ extern crate serde;
 #[cfg(feature = "serde-impls")]
 #[macro_use]
 extern crate serde_derive;
-#[cfg(feature = "file-transport")]
+#[cfg(feature = "jmap-transport")]
+extern crate reqwest;
+#[cfg(any(feature = "file-transport", feature = "jmap-transport"))]
+#[cfg_attr(feature = "jmap-transport", macro_use)]
 extern crate serde_json;
+#[cfg(feature = "async-smtp-transport")]
+extern crate tokio_core;
+#[cfg(feature = "async-smtp-transport")]
+extern crate tokio_io;
+#[cfg(feature = "async-smtp-transport")]
+extern crate tokio_tls;
+#[cfg(feature = "mx-transport")]
+extern crate trust_dns_resolver;
 
 #[cfg(feature = "smtp-transport")]
 pub mod smtp;
+#[cfg(feature = "jmap-transport")]
+pub mod jmap;
+#[cfg(feature = "maildir-transport")]
+pub mod maildir;
+#[cfg(feature = "mx-transport")]
+pub mod mx;
 #[cfg(feature = "sendmail-transport")]
 pub mod sendmail;
 pub mod stub;
@@ -37,12 +56,20 @@ pub mod file;
 
 #[cfg(feature = "file-transport")]
 pub use file::FileTransport;
+#[cfg(feature = "maildir-transport")]
+pub use maildir::MaildirTransport;
 #[cfg(feature = "sendmail-transport")]
 pub use sendmail::SendmailTransport;
 #[cfg(feature = "smtp-transport")]
 pub use smtp::{ClientSecurity, SmtpClient, SmtpTransport};
 #[cfg(feature = "smtp-transport")]
 pub use smtp::client::net::ClientTlsParameters;
+#[cfg(feature = "async-smtp-transport")]
+pub use smtp::async_transport::{AsyncClientSecurity, AsyncSmtpTransport, AsyncTransport, Credentials};
+#[cfg(feature = "mx-transport")]
+pub use mx::{DnsMxResolver, DomainResult, MxHost, MxResolver, MxTransport};
+#[cfg(feature = "jmap-transport")]
+pub use jmap::JmapTransport;
 use std::fmt::{self, Display, Formatter};
 use std::io::Read;
 use std::io::Cursor;
@@ -51,7 +78,7 @@ use std::error::Error as StdError;
 use std::str::FromStr;
 
 /// Error type for email content
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Error {
     /// Missing from in envelope
     MissingFrom,
@@ -90,9 +117,25 @@ pub type EmailResult<T> = Result<T, Error>;
 pub struct EmailAddress(String);
 
 impl EmailAddress {
-    /// Creates a new `EmailAddress`. For now it makes no validation.
+    /// Creates a new `EmailAddress`, checking that it looks like `local-part@domain`
     pub fn new(address: String) -> EmailResult<EmailAddress> {
-        // TODO make some basic sanity checks
+        let at_count = address.matches('@').count();
+        if at_count != 1 {
+            return Err(Error::InvalidEmailAddress);
+        }
+
+        let mut parts = address.splitn(2, '@');
+        let local_part = parts.next().unwrap_or_default();
+        let domain = parts.next().unwrap_or_default();
+
+        if local_part.is_empty() || domain.is_empty() {
+            return Err(Error::InvalidEmailAddress);
+        }
+
+        if address.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(Error::InvalidEmailAddress);
+        }
+
         Ok(EmailAddress(address))
     }
 }
@@ -111,6 +154,86 @@ impl Display for EmailAddress {
     }
 }
 
+/// An address with an optional display name, as used in message headers
+///
+/// This is the `"Display Name" <user@host>` form from
+/// [RFC 5322](https://tools.ietf.org/html/rfc5322#section-3.4). `Envelope`
+/// deliberately keeps using bare `EmailAddress` (addr-spec only, as sent in
+/// `MAIL FROM`/`RCPT TO`); `Mailbox` is for header fields like `From`/`To`
+/// where a friendly name is appropriate.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde-impls", derive(Serialize, Deserialize))]
+pub struct Mailbox {
+    /// Optional display name
+    pub name: Option<String>,
+    /// The address itself
+    pub address: EmailAddress,
+}
+
+impl Mailbox {
+    /// Creates a new `Mailbox` with an optional display name
+    pub fn new(name: Option<String>, address: EmailAddress) -> Mailbox {
+        Mailbox { name, address }
+    }
+}
+
+impl FromStr for Mailbox {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match (s.find('<'), s.ends_with('>')) {
+            (Some(start), true) => {
+                let name = s[..start].trim().trim_matches('"').trim();
+                let address = &s[start + 1..s.len() - 1];
+                let name = if name.is_empty() {
+                    None
+                } else {
+                    Some(name.to_string())
+                };
+                Ok(Mailbox::new(name, EmailAddress::from_str(address)?))
+            }
+            _ => Ok(Mailbox::new(None, EmailAddress::from_str(s)?)),
+        }
+    }
+}
+
+/// RFC 5322 `specials`: characters that cannot appear in an unquoted display name
+const DISPLAY_NAME_SPECIALS: &str = "()<>[]:;@\\,.\"";
+
+/// Escapes `"` and `\`, as required inside an RFC 5322 quoted-string
+fn escape_quoted(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c == '"' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl Display for Mailbox {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.name {
+            Some(ref name) => {
+                if name.chars().any(|c| DISPLAY_NAME_SPECIALS.contains(c)) {
+                    write!(f, "\"{}\" <{}>", escape_quoted(name), self.address)
+                } else {
+                    write!(f, "{} <{}>", name, self.address)
+                }
+            }
+            None => write!(f, "{}", self.address),
+        }
+    }
+}
+
+impl From<EmailAddress> for Mailbox {
+    fn from(address: EmailAddress) -> Self {
+        Mailbox::new(None, address)
+    }
+}
+
 /// Simple email envelope representation
 ///
 /// We only accept mailboxes, and do not support source routes (as per RFC).
@@ -217,3 +340,84 @@ pub trait Transport<'a> {
     /// Sends the email
     fn send(&mut self, email: SendableEmail) -> Self::Result;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn email_address_accepts_a_well_formed_address() {
+        assert!(EmailAddress::new("user@example.com".to_string()).is_ok());
+    }
+
+    #[test]
+    fn email_address_rejects_missing_at_sign() {
+        assert_eq!(
+            EmailAddress::new("userexample.com".to_string()),
+            Err(Error::InvalidEmailAddress)
+        );
+    }
+
+    #[test]
+    fn email_address_rejects_multiple_at_signs() {
+        assert_eq!(
+            EmailAddress::new("user@ex@ample.com".to_string()),
+            Err(Error::InvalidEmailAddress)
+        );
+    }
+
+    #[test]
+    fn email_address_rejects_empty_local_part_or_domain() {
+        assert_eq!(
+            EmailAddress::new("@example.com".to_string()),
+            Err(Error::InvalidEmailAddress)
+        );
+        assert_eq!(
+            EmailAddress::new("user@".to_string()),
+            Err(Error::InvalidEmailAddress)
+        );
+    }
+
+    #[test]
+    fn email_address_rejects_whitespace() {
+        assert_eq!(
+            EmailAddress::new("us er@example.com".to_string()),
+            Err(Error::InvalidEmailAddress)
+        );
+    }
+
+    #[test]
+    fn mailbox_from_str_parses_name_and_address() {
+        let mailbox = Mailbox::from_str("John Doe <john@example.com>").unwrap();
+        assert_eq!(mailbox.name, Some("John Doe".to_string()));
+        assert_eq!(mailbox.address.to_string(), "john@example.com");
+    }
+
+    #[test]
+    fn mailbox_from_str_parses_bare_address() {
+        let mailbox = Mailbox::from_str("john@example.com").unwrap();
+        assert_eq!(mailbox.name, None);
+        assert_eq!(mailbox.address.to_string(), "john@example.com");
+    }
+
+    #[test]
+    fn mailbox_display_does_not_quote_a_plain_name() {
+        let mailbox = Mailbox::new(
+            Some("John Doe".to_string()),
+            EmailAddress::new("john@example.com".to_string()).unwrap(),
+        );
+        assert_eq!(mailbox.to_string(), "John Doe <john@example.com>");
+    }
+
+    #[test]
+    fn mailbox_display_quotes_and_escapes_a_name_with_specials() {
+        let mailbox = Mailbox::new(
+            Some(r#"Doe, John "JD""#.to_string()),
+            EmailAddress::new("john@example.com".to_string()).unwrap(),
+        );
+        assert_eq!(
+            mailbox.to_string(),
+            r#""Doe, John \"JD\"" <john@example.com>"#
+        );
+    }
+}
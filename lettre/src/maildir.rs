@@ -0,0 +1,104 @@
+//! The Maildir transport writes each message as raw RFC 822 bytes into a
+//! standard [Maildir](http://www.qmail.org/man/man5/maildir.html) directory
+//! structure, so the output can be read directly by any Maildir-aware mail
+//! client or IMAP server.
+
+use hostname;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use {SendableEmail, Transport};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Writes the message content into a Maildir directory
+#[derive(Debug, Clone)]
+pub struct MaildirTransport {
+    path: PathBuf,
+}
+
+impl MaildirTransport {
+    /// Creates a new transport writing into the given Maildir
+    ///
+    /// Nothing is created here: each `send` creates `path`, along with its
+    /// `tmp/`, `new/` and `cur/` subdirectories, if they do not already exist.
+    pub fn new<P: AsRef<Path>>(path: P) -> MaildirTransport {
+        MaildirTransport {
+            path: PathBuf::from(path.as_ref()),
+        }
+    }
+
+    fn create_dirs(&self) -> Result<(), ::std::io::Error> {
+        for sub in &["tmp", "new", "cur"] {
+            fs::create_dir_all(self.path.join(sub))?;
+        }
+        Ok(())
+    }
+
+    /// Builds a unique file name, following the Maildir naming convention of
+    /// `<timestamp>.<pid>_<counter>.<hostname>`
+    fn unique_name(&self) -> String {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let pid = process::id();
+        let counter = COUNTER.fetch_add(1, Ordering::SeqCst);
+        let host = hostname::get_hostname().unwrap_or_else(|| "localhost".to_string());
+        format!("{}.{}_{}.{}", timestamp, pid, counter, host)
+    }
+}
+
+impl<'a> Transport<'a> for MaildirTransport {
+    type Result = Result<(), ::std::io::Error>;
+
+    fn send(&mut self, email: SendableEmail) -> Self::Result {
+        self.create_dirs()?;
+
+        let name = self.unique_name();
+        let tmp_path = self.path.join("tmp").join(&name);
+        let new_path = self.path.join("new").join(&name);
+
+        let mut bytes = Vec::new();
+        email.message().read_to_end(&mut bytes)?;
+        {
+            let mut f = File::create(&tmp_path)?;
+            f.write_all(&bytes)?;
+            f.sync_all()?;
+        }
+        fs::rename(&tmp_path, &new_path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_name_has_the_timestamp_pid_counter_hostname_shape() {
+        let transport = MaildirTransport::new("/tmp/doesnt-need-to-exist");
+        let name = transport.unique_name();
+
+        let mut parts = name.splitn(3, '.');
+        let timestamp = parts.next().unwrap();
+        let pid_counter = parts.next().unwrap();
+        let host = parts.next().unwrap();
+
+        assert!(timestamp.parse::<u64>().is_ok());
+        assert!(pid_counter.contains('_'));
+        let mut pid_counter_parts = pid_counter.splitn(2, '_');
+        assert!(pid_counter_parts.next().unwrap().parse::<u32>().is_ok());
+        assert!(pid_counter_parts.next().unwrap().parse::<usize>().is_ok());
+        assert!(!host.is_empty());
+    }
+
+    #[test]
+    fn unique_name_is_different_on_each_call() {
+        let transport = MaildirTransport::new("/tmp/doesnt-need-to-exist");
+        assert_ne!(transport.unique_name(), transport.unique_name());
+    }
+}
@@ -0,0 +1,324 @@
+//! The MX transport delivers mail directly to each recipient's mail server,
+//! by resolving MX records, instead of relaying through a single configured
+//! smarthost.
+
+use smtp::client::{Client, Reply};
+use smtp::error::Error;
+use std::collections::HashMap;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
+use trust_dns_resolver::Resolver;
+use {EmailAddress, Envelope, SendableEmail, Transport};
+
+static TIE_BREAK_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A mail exchanger host for a domain, with its DNS preference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MxHost {
+    /// The hostname to connect to
+    pub exchange: String,
+    /// The 16-bit DNS preference value; lower is tried first
+    pub preference: u16,
+}
+
+/// Resolves the candidate mail exchanger hosts for a domain
+///
+/// Implementors look up MX records for `domain`, sorted by preference
+/// ascending (with ties randomized), falling back to the domain's own A/AAAA
+/// record as an implicit MX at preference `0` when no MX record exists.
+pub trait MxResolver {
+    /// Resolves the candidate hosts for `domain`
+    fn resolve(&self, domain: &str) -> Result<Vec<MxHost>, Error>;
+}
+
+/// A `MxResolver` backed by a configured `trust-dns-resolver`
+pub struct DnsMxResolver {
+    resolver: Resolver,
+    hosts_per_domain: usize,
+}
+
+impl DnsMxResolver {
+    /// Creates a new resolver, trying at most `hosts_per_domain` hosts per domain
+    pub fn new(hosts_per_domain: usize) -> Result<DnsMxResolver, Error> {
+        let resolver = Resolver::new(ResolverConfig::default(), ResolverOpts::default())
+            .map_err(|err| Error::Resolution(err.to_string()))?;
+        Ok(DnsMxResolver {
+            resolver,
+            hosts_per_domain,
+        })
+    }
+}
+
+impl Default for DnsMxResolver {
+    fn default() -> DnsMxResolver {
+        DnsMxResolver::new(5).expect("failed to initialize the default DNS resolver")
+    }
+}
+
+impl MxResolver for DnsMxResolver {
+    fn resolve(&self, domain: &str) -> Result<Vec<MxHost>, Error> {
+        let mut hosts = match self.resolver.mx_lookup(domain) {
+            Ok(lookup) => lookup
+                .iter()
+                .map(|mx| MxHost {
+                    exchange: mx.exchange().to_utf8().trim_end_matches('.').to_string(),
+                    preference: mx.preference(),
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        if hosts.is_empty() {
+            // No MX record: the domain itself is an implicit MX at preference 0,
+            // provided it resolves to an address at all.
+            if self.resolver.lookup_ip(domain).is_ok() {
+                hosts.push(MxHost {
+                    exchange: domain.to_string(),
+                    preference: 0,
+                });
+            }
+        }
+        sort_by_preference(&mut hosts);
+        hosts.truncate(self.hosts_per_domain);
+        Ok(hosts)
+    }
+}
+
+/// Sorts hosts by ascending preference, randomizing the order of ties
+fn sort_by_preference(hosts: &mut Vec<MxHost>) {
+    // Hosts sharing a preference value are interchangeable per RFC 5321 §5.1;
+    // shuffling them spreads load across equally-preferred exchangers instead
+    // of always hammering the first one listed.
+    shuffle(hosts);
+    hosts.sort_by_key(|host| host.preference);
+}
+
+fn shuffle(hosts: &mut Vec<MxHost>) {
+    // A minimal Fisher-Yates shuffle. The seed mixes the current time, the
+    // process id and a call-scoped atomic counter, so distinct calls (even
+    // within the same millisecond, even across processes) start from
+    // different seeds; two seeds can still yield the same permutation.
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let counter = TIE_BREAK_COUNTER.fetch_add(1, Ordering::SeqCst) as u64;
+    let mut seed = u64::from(nanos) ^ (u64::from(process::id()) << 32) ^ counter;
+
+    for i in (1..hosts.len()).rev() {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        let j = (seed as usize) % (i + 1);
+        hosts.swap(i, j);
+    }
+}
+
+/// Per-recipient-domain delivery outcome
+#[derive(Debug, Clone)]
+pub enum DomainResult {
+    /// The message was accepted by one of the domain's mail exchangers
+    Delivered { exchange: String },
+    /// Every candidate host refused or was unreachable
+    Deferred { last_error: String },
+}
+
+/// Result of a `MxTransport` send: one outcome per recipient domain
+pub type MxResult = Result<HashMap<String, DomainResult>, Error>;
+
+/// Delivers mail directly to recipients' mail servers, resolved via MX lookup
+pub struct MxTransport {
+    resolver: Box<MxResolver + Send>,
+    hello_name: String,
+}
+
+impl MxTransport {
+    /// Creates a transport using the default DNS-backed resolver
+    pub fn new() -> MxTransport {
+        MxTransport::with_resolver(Box::new(DnsMxResolver::default()))
+    }
+
+    /// Creates a transport using a custom `MxResolver`
+    pub fn with_resolver(resolver: Box<MxResolver + Send>) -> MxTransport {
+        MxTransport {
+            resolver,
+            hello_name: "localhost".to_string(),
+        }
+    }
+
+    /// Sets the name used in the `EHLO`/`HELO` command
+    pub fn hello_name(mut self, hello_name: &str) -> MxTransport {
+        self.hello_name = hello_name.to_string();
+        self
+    }
+
+    fn group_by_domain<'a>(&self, addresses: &'a [EmailAddress]) -> HashMap<String, Vec<&'a EmailAddress>> {
+        let mut by_domain: HashMap<String, Vec<&EmailAddress>> = HashMap::new();
+        for address in addresses {
+            let domain = address
+                .to_string()
+                .rsplit('@')
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            by_domain.entry(domain).or_insert_with(Vec::new).push(address);
+        }
+        by_domain
+    }
+
+    fn deliver_to_domain(
+        &self,
+        domain: &str,
+        recipients: &[&EmailAddress],
+        envelope: &Envelope,
+        message: &[u8],
+    ) -> DomainResult {
+        let hosts = match self.resolver.resolve(domain) {
+            Ok(hosts) => hosts,
+            Err(err) => return DomainResult::Deferred { last_error: err.to_string() },
+        };
+
+        let mut last_error = "no mail exchanger found".to_string();
+        for host in hosts {
+            match self.try_deliver(&host.exchange, recipients, envelope, message) {
+                Ok(()) => {
+                    return DomainResult::Delivered { exchange: host.exchange };
+                }
+                Err(err) => {
+                    // Connection failure or any 4xx/5xx refusal: move on to
+                    // the next candidate host for this domain.
+                    last_error = err.to_string();
+                }
+            }
+        }
+        DomainResult::Deferred { last_error }
+    }
+
+    fn try_deliver(
+        &self,
+        exchange: &str,
+        recipients: &[&EmailAddress],
+        envelope: &Envelope,
+        message: &[u8],
+    ) -> Result<(), Error> {
+        let mut client = Client::new();
+        client.connect((exchange, 25), None)?;
+        check_reply(client.read_response()?)?;
+        client.command(format!("EHLO {}", self.hello_name))?;
+        check_reply(client.read_response()?)?;
+
+        let from = envelope.from().map(|a| a.to_string()).unwrap_or_default();
+        client.command(format!("MAIL FROM:<{}>", from))?;
+        check_reply(client.read_response()?)?;
+        for to in recipients {
+            client.command(format!("RCPT TO:<{}>", to))?;
+            check_reply(client.read_response()?)?;
+        }
+        client.command("DATA")?;
+        check_reply(client.read_response()?)?;
+        client.send_message(&mut &message[..])?;
+        check_reply(client.read_response()?)?;
+        client.command("QUIT")?;
+        client.read_response()?;
+        client.close();
+        Ok(())
+    }
+}
+
+/// Turns a `4xx`/`5xx` reply into an error, so the caller's host loop moves on
+/// to the next candidate mail exchanger instead of reporting a false delivery
+fn check_reply(reply: Reply) -> Result<(), Error> {
+    if reply.is_positive() {
+        Ok(())
+    } else if reply.code >= 500 {
+        Err(Error::Permanent(reply.text))
+    } else {
+        Err(Error::Transient(reply.text))
+    }
+}
+
+impl Default for MxTransport {
+    fn default() -> MxTransport {
+        MxTransport::new()
+    }
+}
+
+impl<'a> Transport<'a> for MxTransport {
+    type Result = MxResult;
+
+    fn send(&mut self, email: SendableEmail) -> Self::Result {
+        let envelope = email.envelope().clone();
+        let message = email.message_to_string()?.into_bytes();
+
+        let by_domain = self.group_by_domain(envelope.to());
+        let mut results = HashMap::new();
+        for (domain, recipients) in by_domain {
+            let result = self.deliver_to_domain(&domain, &recipients, &envelope, &message);
+            results.insert(domain, result);
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(exchange: &str, preference: u16) -> MxHost {
+        MxHost {
+            exchange: exchange.to_string(),
+            preference,
+        }
+    }
+
+    #[test]
+    fn sort_by_preference_orders_ascending() {
+        let mut hosts = vec![host("c", 30), host("a", 10), host("b", 20)];
+        sort_by_preference(&mut hosts);
+        let preferences: Vec<u16> = hosts.iter().map(|h| h.preference).collect();
+        assert_eq!(preferences, vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn sort_by_preference_keeps_every_tied_host() {
+        let mut hosts = vec![host("a", 10), host("b", 10), host("c", 10), host("d", 20)];
+        sort_by_preference(&mut hosts);
+        let mut exchanges: Vec<&str> = hosts.iter().map(|h| h.exchange.as_str()).collect();
+        exchanges.sort();
+        assert_eq!(exchanges, vec!["a", "b", "c", "d"]);
+        assert_eq!(hosts.last().unwrap().exchange, "d");
+    }
+
+    #[test]
+    fn check_reply_accepts_2xx_and_3xx() {
+        assert!(check_reply(Reply {
+            code: 250,
+            text: "250 OK".to_string(),
+        }).is_ok());
+        assert!(check_reply(Reply {
+            code: 354,
+            text: "354 start mail input".to_string(),
+        }).is_ok());
+    }
+
+    #[test]
+    fn check_reply_rejects_4xx_as_transient() {
+        match check_reply(Reply {
+            code: 450,
+            text: "450 try again".to_string(),
+        }) {
+            Err(Error::Transient(_)) => {}
+            other => panic!("expected Transient, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn check_reply_rejects_5xx_as_permanent() {
+        match check_reply(Reply {
+            code: 550,
+            text: "550 no such user".to_string(),
+        }) {
+            Err(Error::Permanent(_)) => {}
+            other => panic!("expected Permanent, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,407 @@
+//! An asynchronous, futures-based counterpart to the synchronous SMTP transport.
+//!
+//! Where `SmtpTransport` opens one connection and drives the conversation to
+//! completion before returning, `AsyncSmtpTransport` drives it as a chain of
+//! futures, so a server can hold many deliveries in flight at once instead of
+//! sending them one at a time. Connections are not pooled: each `send` opens
+//! its own connection to the server.
+
+use base64;
+use futures::future::{loop_fn, Loop};
+use futures::Future;
+use smtp::client::net::ClientTlsParameters;
+use smtp::error::Error;
+use std::io;
+use std::net::SocketAddr;
+use tokio_core::net::TcpStream;
+use tokio_core::reactor::Handle;
+use tokio_io::io::{copy, read_until, write_all};
+use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_tls::{TlsConnectorExt, TlsStream};
+use {Envelope, Message, SendableEmail};
+
+/// The asynchronous counterpart of `Transport`
+///
+/// Implementors drive the send to completion as a `Future` instead of
+/// blocking the calling thread, so callers can run many sends concurrently.
+pub trait AsyncTransport<'a> {
+    /// Result type for the asynchronous send
+    type Result: Future<Item = Self::Item, Error = Self::Error>;
+    /// Successful result of the send
+    type Item;
+    /// Error produced by a failed send
+    type Error;
+
+    /// Sends the email asynchronously
+    fn send(&'a mut self, email: SendableEmail) -> Self::Result;
+}
+
+/// Username/password pair sent with `AUTH PLAIN`
+#[derive(Clone)]
+pub struct Credentials {
+    /// Account name
+    pub username: String,
+    /// Account password
+    pub password: String,
+}
+
+impl Credentials {
+    /// Creates a new set of credentials
+    pub fn new(username: String, password: String) -> Credentials {
+        Credentials { username, password }
+    }
+
+    /// Encodes the credentials for an `AUTH PLAIN` command:
+    /// base64(`\0username\0password`)
+    fn plain_response(&self) -> String {
+        let mut raw = Vec::new();
+        raw.push(0u8);
+        raw.extend_from_slice(self.username.as_bytes());
+        raw.push(0u8);
+        raw.extend_from_slice(self.password.as_bytes());
+        base64::encode(&raw)
+    }
+}
+
+/// Configuration for connecting to an SMTP server asynchronously
+#[derive(Clone)]
+pub struct AsyncSmtpTransport {
+    server_addr: SocketAddr,
+    hello_name: String,
+    security: AsyncClientSecurity,
+    credentials: Option<Credentials>,
+    handle: Handle,
+}
+
+/// `ClientSecurity` restricted to the modes the async client currently supports
+///
+/// `AsyncSmtpTransport` does not yet support opportunistic `STARTTLS`; only
+/// plaintext and TLS-wrapped connections are implemented.
+#[derive(Clone)]
+pub enum AsyncClientSecurity {
+    /// Insecure connection
+    None,
+    /// Connect on a TLS-wrapped port, such as 465
+    Wrapper(ClientTlsParameters),
+}
+
+impl AsyncSmtpTransport {
+    /// Creates a new asynchronous SMTP transport, bound to the given reactor
+    pub fn new(
+        server_addr: SocketAddr,
+        hello_name: &str,
+        security: AsyncClientSecurity,
+        handle: &Handle,
+    ) -> AsyncSmtpTransport {
+        AsyncSmtpTransport {
+            server_addr,
+            hello_name: hello_name.to_string(),
+            security,
+            credentials: None,
+            handle: handle.clone(),
+        }
+    }
+
+    /// Sets the credentials to authenticate with via `AUTH PLAIN`
+    pub fn credentials(mut self, credentials: Credentials) -> AsyncSmtpTransport {
+        self.credentials = Some(credentials);
+        self
+    }
+}
+
+impl<'a> AsyncTransport<'a> for AsyncSmtpTransport {
+    type Result = Box<Future<Item = (), Error = Error> + Send>;
+    type Item = ();
+    type Error = Error;
+
+    fn send(&'a mut self, email: SendableEmail) -> Self::Result {
+        let hello_name = self.hello_name.clone();
+        let credentials = self.credentials.clone();
+        let envelope = email.envelope().clone();
+        let message = email.message();
+
+        let future = connect(&self.server_addr, &self.handle, self.security.clone())
+            .and_then(move |stream| read_reply(stream))
+            .and_then(|(stream, reply)| expect_code(stream, reply, 220))
+            .and_then(move |stream| {
+                write_command(stream, format!("EHLO {}", hello_name))
+            })
+            .and_then(|stream| read_reply(stream))
+            .and_then(|(stream, reply)| expect_code(stream, reply, 250))
+            .and_then(move |stream| authenticate(stream, credentials))
+            .and_then(move |stream| send_envelope(stream, envelope))
+            .and_then(|stream| write_command(stream, "DATA".to_string()))
+            .and_then(|stream| read_reply(stream))
+            .and_then(|(stream, reply)| expect_code(stream, reply, 354))
+            .and_then(move |stream| stream_message(stream, message))
+            .and_then(|stream| read_reply(stream))
+            .and_then(|(stream, reply)| expect_code(stream, reply, 250))
+            .and_then(|stream| write_command(stream, "QUIT".to_string()))
+            .map(|_| ());
+
+        Box::new(future)
+    }
+}
+
+/// Wraps the underlying connection, plaintext or TLS, behind one `AsyncRead + AsyncWrite` type
+enum AsyncNetworkStream {
+    Tcp(TcpStream),
+    Tls(TlsStream<TcpStream>),
+}
+
+impl io::Read for AsyncNetworkStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            AsyncNetworkStream::Tcp(ref mut s) => s.read(buf),
+            AsyncNetworkStream::Tls(ref mut s) => s.read(buf),
+        }
+    }
+}
+
+impl io::Write for AsyncNetworkStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            AsyncNetworkStream::Tcp(ref mut s) => s.write(buf),
+            AsyncNetworkStream::Tls(ref mut s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            AsyncNetworkStream::Tcp(ref mut s) => s.flush(),
+            AsyncNetworkStream::Tls(ref mut s) => s.flush(),
+        }
+    }
+}
+
+// `TcpStream` and `TlsStream` are both already `AsyncRead`/`AsyncWrite`, and
+// this enum's `Read`/`Write` impls just forward to them, so the default
+// `prepare_uninitialized_buffer` (which assumes nothing about the reader) is
+// sound here too.
+#[allow(unsafe_code)]
+unsafe impl AsyncRead for AsyncNetworkStream {}
+
+impl AsyncWrite for AsyncNetworkStream {
+    fn shutdown(&mut self) -> ::futures::Poll<(), io::Error> {
+        match *self {
+            AsyncNetworkStream::Tcp(ref mut s) => AsyncWrite::shutdown(s),
+            AsyncNetworkStream::Tls(ref mut s) => AsyncWrite::shutdown(s),
+        }
+    }
+}
+
+fn connect(
+    server_addr: &SocketAddr,
+    handle: &Handle,
+    security: AsyncClientSecurity,
+) -> Box<Future<Item = AsyncNetworkStream, Error = Error> + Send> {
+    let tcp = TcpStream::connect(server_addr, handle).from_err();
+    match security {
+        AsyncClientSecurity::None => Box::new(tcp.map(AsyncNetworkStream::Tcp)),
+        AsyncClientSecurity::Wrapper(params) => Box::new(tcp.and_then(move |tcp_stream| {
+            params
+                .connector
+                .connect_async(&params.domain, tcp_stream)
+                .map(AsyncNetworkStream::Tls)
+                .map_err(|err| Error::Tls(Box::new(err)))
+        })),
+    }
+}
+
+/// A parsed SMTP reply: its 3-digit status code and the full (possibly
+/// multi-line) reply text
+struct Reply {
+    code: u16,
+    text: String,
+}
+
+/// Fails with a `Transient`/`Permanent` error built from `reply` unless its
+/// code matches `expected`
+fn expect_code<S>(
+    stream: S,
+    reply: Reply,
+    expected: u16,
+) -> Box<Future<Item = S, Error = Error> + Send>
+where
+    S: Send + 'static,
+{
+    if reply.code == expected {
+        Box::new(::futures::future::ok(stream))
+    } else if reply.code >= 500 {
+        Box::new(::futures::future::err(Error::Permanent(reply.text)))
+    } else {
+        Box::new(::futures::future::err(Error::Transient(reply.text)))
+    }
+}
+
+fn write_command<S: AsyncWrite>(
+    stream: S,
+    mut command: String,
+) -> Box<Future<Item = S, Error = Error> + Send>
+where
+    S: Send + 'static,
+{
+    command.push_str("\r\n");
+    Box::new(
+        write_all(stream, command.into_bytes())
+            .from_err()
+            .map(|(stream, _)| stream),
+    )
+}
+
+/// Reads a full, possibly multi-line, SMTP reply
+///
+/// Each line of a multi-line reply repeats the status code followed by `-`
+/// except the last, which uses a space; reading a single line and moving on
+/// would leave the continuation lines unread in the stream, desynchronizing
+/// every command/response pair that follows.
+fn read_reply<S>(stream: S) -> Box<Future<Item = (S, Reply), Error = Error> + Send>
+where
+    S: AsyncRead + Send + 'static,
+{
+    Box::new(
+        loop_fn((stream, String::new()), |(stream, acc)| {
+            read_until(stream, b'\n', Vec::new())
+                .from_err()
+                .map(move |(stream, buf)| {
+                    let line = String::from_utf8_lossy(&buf).into_owned();
+                    let mut acc = acc;
+                    acc.push_str(&line);
+                    let is_final = line.as_bytes().get(3) != Some(&b'-');
+                    if is_final {
+                        Loop::Break((stream, acc))
+                    } else {
+                        Loop::Continue((stream, acc))
+                    }
+                })
+        }).and_then(|(stream, text)| {
+            let code = text
+                .get(..3)
+                .and_then(|code| code.parse::<u16>().ok())
+                .ok_or_else(|| Error::Client("could not parse SMTP reply code"))?;
+            Ok((stream, Reply { code, text }))
+        }),
+    )
+}
+
+fn authenticate<S: AsyncWrite + AsyncRead>(
+    stream: S,
+    credentials: Option<Credentials>,
+) -> Box<Future<Item = S, Error = Error> + Send>
+where
+    S: Send + 'static,
+{
+    match credentials {
+        None => Box::new(::futures::future::ok(stream)),
+        Some(credentials) => Box::new(
+            write_command(
+                stream,
+                format!("AUTH PLAIN {}", credentials.plain_response()),
+            ).and_then(|stream| read_reply(stream))
+                .and_then(|(stream, reply)| expect_code(stream, reply, 235)),
+        ),
+    }
+}
+
+fn send_envelope<S: AsyncWrite + AsyncRead>(
+    stream: S,
+    envelope: Envelope,
+) -> Box<Future<Item = S, Error = Error> + Send>
+where
+    S: Send + 'static,
+{
+    let from = envelope.from().map(|a| a.to_string()).unwrap_or_default();
+    let mut commands = vec![format!("MAIL FROM:<{}>", from)];
+    commands.extend(envelope.to().iter().map(|to| format!("RCPT TO:<{}>", to)));
+
+    commands.into_iter().fold(
+        Box::new(::futures::future::ok(stream)) as Box<Future<Item = S, Error = Error> + Send>,
+        |acc, command| {
+            Box::new(
+                acc.and_then(|stream| write_command(stream, command))
+                    .and_then(|stream| read_reply(stream))
+                    .and_then(|(stream, reply)| expect_code(stream, reply, 250)),
+            )
+        },
+    )
+}
+
+/// Wraps a `Message` so it can be driven through `tokio_io::io::copy`
+///
+/// `Message` (and the `Box<Read + Send>` behind its `Reader` variant) only
+/// promises synchronous, non-blocking-when-ready reads, which is all
+/// `AsyncRead` requires; it never needs to keep its own uninitialized-buffer
+/// guarantees, so the default `prepare_uninitialized_buffer` is sound here.
+struct AsyncMessage(Message);
+
+impl io::Read for AsyncMessage {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[allow(unsafe_code)]
+unsafe impl AsyncRead for AsyncMessage {}
+
+/// Streams the message body to the server without buffering it fully in memory first
+///
+/// `SendableEmail::message()` may yield a `Message::Reader`, in which case the
+/// bytes are read and written to the socket in chunks as they become
+/// available, rather than being collected into a single in-memory `Vec` up
+/// front.
+fn stream_message<S: AsyncWrite + 'static + Send>(
+    stream: S,
+    message: Message,
+) -> Box<Future<Item = S, Error = Error> + Send> {
+    Box::new(
+        copy(AsyncMessage(message), stream)
+            .from_err()
+            .and_then(|(_, _, stream)| write_command(stream, "\r\n.".to_string())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_response_is_null_separated_and_base64_encoded() {
+        let creds = Credentials::new("user".to_string(), "pass".to_string());
+        let decoded = base64::decode(&creds.plain_response()).unwrap();
+        assert_eq!(decoded, b"\0user\0pass");
+    }
+
+    #[test]
+    fn expect_code_accepts_matching_code() {
+        let reply = Reply {
+            code: 250,
+            text: "250 OK".to_string(),
+        };
+        let stream = expect_code(1i32, reply, 250).wait().unwrap();
+        assert_eq!(stream, 1);
+    }
+
+    #[test]
+    fn expect_code_rejects_permanent_failure() {
+        let reply = Reply {
+            code: 550,
+            text: "550 no such user".to_string(),
+        };
+        match expect_code(1i32, reply, 250).wait() {
+            Err(Error::Permanent(ref text)) => assert_eq!(text, "550 no such user"),
+            other => panic!("expected a Permanent error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn expect_code_rejects_transient_failure() {
+        let reply = Reply {
+            code: 450,
+            text: "450 try again later".to_string(),
+        };
+        match expect_code(1i32, reply, 250).wait() {
+            Err(Error::Transient(_)) => {}
+            other => panic!("expected a Transient error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! SMTP client
+
+pub mod net;
+
+use bufstream::BufStream;
+use smtp::error::Error;
+use std::io::{BufRead, Read, Write};
+use std::net::ToSocketAddrs;
+
+use self::net::{ClientTlsParameters, NetworkStream};
+
+/// A parsed SMTP reply: its 3-digit status code and the full (possibly
+/// multi-line) reply text
+#[derive(Debug, Clone)]
+pub struct Reply {
+    /// The 3-digit status code, e.g. `250`
+    pub code: u16,
+    /// The full reply text, continuation lines included
+    pub text: String,
+}
+
+impl Reply {
+    /// Whether `code` is a `2xx` or `3xx` (non-error) reply
+    pub fn is_positive(&self) -> bool {
+        self.code < 400
+    }
+}
+
+/// A low-level SMTP client, built on top of a `NetworkStream`
+///
+/// It only knows how to write raw commands and read raw responses: higher
+/// level transports are responsible for driving the SMTP conversation
+/// (EHLO, AUTH, MAIL, RCPT, DATA, ...) on top of it.
+pub struct Client {
+    stream: Option<BufStream<NetworkStream>>,
+}
+
+impl Default for Client {
+    fn default() -> Client {
+        Client { stream: None }
+    }
+}
+
+impl Client {
+    /// Creates a new SMTP client
+    pub fn new() -> Client {
+        Client::default()
+    }
+
+    /// Connects to the configured server, optionally upgrading to TLS right away
+    pub fn connect<A: ToSocketAddrs>(
+        &mut self,
+        addr: A,
+        tls_parameters: Option<&ClientTlsParameters>,
+    ) -> Result<(), Error> {
+        let tcp_stream = ::std::net::TcpStream::connect(addr)?;
+        let stream = match tls_parameters {
+            Some(params) => {
+                let tls_stream = params
+                    .connector
+                    .connect(&params.domain, tcp_stream)
+                    .map_err(|err| Error::Tls(Box::new(err)))?;
+                NetworkStream::Tls(tls_stream)
+            }
+            None => NetworkStream::Tcp(tcp_stream),
+        };
+        self.stream = Some(BufStream::new(stream));
+        Ok(())
+    }
+
+    /// Upgrades an already connected, plaintext connection to TLS (STARTTLS)
+    pub fn upgrade_tls(&mut self, tls_parameters: &ClientTlsParameters) -> Result<(), Error> {
+        let stream = self.stream.take().ok_or(Error::Client("not connected"))?;
+        let tcp_stream = match stream.into_inner() {
+            NetworkStream::Tcp(s) => s,
+            _ => return Err(Error::Client("connection is already encrypted")),
+        };
+        let tls_stream = tls_parameters
+            .connector
+            .connect(&tls_parameters.domain, tcp_stream)
+            .map_err(|err| Error::Tls(Box::new(err)))?;
+        self.stream = Some(BufStream::new(NetworkStream::Tls(tls_stream)));
+        Ok(())
+    }
+
+    /// Sends a raw command line to the server
+    pub fn command<S: AsRef<str>>(&mut self, command: S) -> Result<(), Error> {
+        let stream = self.stream.as_mut().ok_or(Error::Client("not connected"))?;
+        stream.write_all(command.as_ref().as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    /// Reads a full (possibly multi-line) response from the server
+    ///
+    /// A reply can span several lines, each but the last marked with a `-`
+    /// right after the status code (e.g. `250-STARTTLS` then `250 HELP`); we
+    /// keep reading lines until we see one with a space in that position.
+    pub fn read_response(&mut self) -> Result<Reply, Error> {
+        let stream = self.stream.as_mut().ok_or(Error::Client("not connected"))?;
+        parse_reply(stream)
+    }
+
+    /// Writes the message body, ending it with the SMTP data terminator
+    pub fn send_message<R: Read>(&mut self, message: &mut R) -> Result<(), Error> {
+        let stream = self.stream.as_mut().ok_or(Error::Client("not connected"))?;
+        ::std::io::copy(message, stream)?;
+        stream.write_all(b"\r\n.\r\n")?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    /// Closes the connection
+    pub fn close(&mut self) {
+        if let Some(ref mut stream) = self.stream {
+            let _ = stream.get_mut().shutdown();
+        }
+        self.stream = None;
+    }
+}
+
+/// Reads a full, possibly multi-line, reply off of any `BufRead`
+///
+/// Pulled out of `Client::read_response` so the parsing logic can be
+/// exercised directly against an in-memory buffer in tests, without needing
+/// a real `NetworkStream`.
+fn parse_reply<R: BufRead>(stream: &mut R) -> Result<Reply, Error> {
+    let mut full = String::new();
+    loop {
+        let mut line = String::new();
+        stream.read_line(&mut line)?;
+        if line.is_empty() {
+            return Err(Error::Client("unexpected end of stream"));
+        }
+        let is_final_line = line.as_bytes().get(3) != Some(&b'-');
+        full.push_str(&line);
+        if is_final_line {
+            break;
+        }
+    }
+    let code = full
+        .get(..3)
+        .and_then(|c| c.parse::<u16>().ok())
+        .ok_or(Error::Client("could not parse SMTP reply code"))?;
+    Ok(Reply { code, text: full })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn parse_reply_reads_a_single_line_reply() {
+        let mut stream = Cursor::new(b"250 OK\r\n".to_vec());
+        let reply = parse_reply(&mut stream).unwrap();
+        assert_eq!(reply.code, 250);
+        assert_eq!(reply.text, "250 OK\r\n");
+    }
+
+    #[test]
+    fn parse_reply_reads_every_continuation_line() {
+        let mut stream = Cursor::new(b"250-STARTTLS\r\n250-8BITMIME\r\n250 HELP\r\n".to_vec());
+        let reply = parse_reply(&mut stream).unwrap();
+        assert_eq!(reply.code, 250);
+        assert_eq!(
+            reply.text,
+            "250-STARTTLS\r\n250-8BITMIME\r\n250 HELP\r\n"
+        );
+        assert!(reply.text.contains("STARTTLS"));
+    }
+
+    #[test]
+    fn parse_reply_rejects_an_unparsable_code() {
+        let mut stream = Cursor::new(b"nope\r\n".to_vec());
+        assert!(parse_reply(&mut stream).is_err());
+    }
+}
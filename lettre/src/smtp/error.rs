@@ -0,0 +1,81 @@
+//! Error and result type for the SMTP transport
+
+use nom;
+use std::error::Error as StdError;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+/// An enum of all error kinds
+#[derive(Debug)]
+pub enum Error {
+    /// Transient SMTP error, 4xx reply code
+    ///
+    /// This is not a fatal error, the server is expected to accept at least
+    /// some of these after a delay.
+    Transient(String),
+    /// Permanent SMTP error, 5xx reply code
+    Permanent(String),
+    /// Error parsing a response
+    Parsing(nom::ErrorKind),
+    /// Internal client error
+    Client(&'static str),
+    /// DNS resolution error
+    Resolution(String),
+    /// `STARTTLS` was required but the server did not advertise support for it
+    StartTlsRequired,
+    /// IO error
+    Io(io::Error),
+    /// TLS error
+    Tls(Box<StdError + Send + Sync>),
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Transient(_) => "a transient error occurred during the SMTP transaction",
+            Error::Permanent(_) => "a permanent error occurred during the SMTP transaction",
+            Error::Parsing(_) => "could not parse the SMTP server response",
+            Error::Client(_) => "an unknown error occurred",
+            Error::Resolution(_) => "could not resolve hostname",
+            Error::StartTlsRequired => {
+                "STARTTLS was required but is not supported by the server"
+            }
+            Error::Io(_) => "an I/O error occurred",
+            Error::Tls(_) => "an encryption error occurred",
+        }
+    }
+
+    fn cause(&self) -> Option<&StdError> {
+        match *self {
+            Error::Io(ref err) => Some(&*err),
+            Error::Tls(ref err) => Some(&**err),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::Transient(ref s) | Error::Permanent(ref s) | Error::Resolution(ref s) => {
+                fmt.write_str(s)
+            }
+            _ => fmt.write_str(self.description()),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<nom::ErrorKind> for Error {
+    fn from(err: nom::ErrorKind) -> Error {
+        Error::Parsing(err)
+    }
+}
+
+/// SMTP result type
+pub type SmtpResult = Result<(), Error>;
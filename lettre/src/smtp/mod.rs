@@ -0,0 +1,211 @@
+//! The SMTP transport sends emails using the SMTP protocol.
+//!
+//! This SMTP client follows [RFC 5321](https://tools.ietf.org/html/rfc5321), and is designed to
+//! efficiently send emails from an application to a relay email server.
+
+#[cfg(feature = "async-smtp-transport")]
+pub mod async_transport;
+pub mod client;
+pub mod error;
+
+use smtp::client::net::ClientTlsParameters;
+use smtp::client::{Client, Reply};
+use smtp::error::Error;
+use std::net::ToSocketAddrs;
+use {Envelope, SendableEmail, Transport};
+
+/// How should the connection to the server be secured
+#[derive(Clone)]
+pub enum ClientSecurity {
+    /// Plaintext connection, no encryption at all
+    None,
+    /// Upgrade to `STARTTLS` if the server advertises support for it,
+    /// otherwise continue in plaintext
+    Opportunistic(ClientTlsParameters),
+    /// Require `STARTTLS`; abort with `Error::StartTlsRequired` if the server
+    /// does not offer it instead of silently sending in plaintext
+    Required(ClientTlsParameters),
+    /// Connect already wrapped in TLS, such as on the legacy submission port 465
+    Wrapper(ClientTlsParameters),
+}
+
+impl ClientSecurity {
+    /// The port conventionally associated with this security mode:
+    /// 25 for plaintext, 587 for the submission port used by `STARTTLS`
+    /// (opportunistic or required), 465 for implicit TLS-on-connect.
+    pub fn default_port(&self) -> u16 {
+        match *self {
+            ClientSecurity::None => 25,
+            ClientSecurity::Opportunistic(_) | ClientSecurity::Required(_) => 587,
+            ClientSecurity::Wrapper(_) => 465,
+        }
+    }
+}
+
+/// Contains client configuration
+pub struct SmtpClient {
+    server_addr: String,
+    hello_name: String,
+    security: ClientSecurity,
+}
+
+impl SmtpClient {
+    /// Creates a new SMTP client for an explicit `host:port` address
+    pub fn new<A: ToSocketAddrs>(
+        addr: A,
+        security: ClientSecurity,
+    ) -> Result<SmtpClient, Error> {
+        let server_addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or(Error::Client("could not resolve server address"))?
+            .to_string();
+        Ok(SmtpClient {
+            server_addr,
+            hello_name: "localhost".to_string(),
+            security,
+        })
+    }
+
+    /// Creates a new SMTP client for `domain`, using the default port for
+    /// the chosen `ClientSecurity` (25 plaintext, 587 STARTTLS, 465 implicit TLS)
+    pub fn new_simple(domain: &str, security: ClientSecurity) -> Result<SmtpClient, Error> {
+        let port = security.default_port();
+        SmtpClient::new((domain, port), security)
+    }
+
+    /// Sets the name used in the `EHLO`/`HELO` command
+    pub fn hello_name(mut self, hello_name: &str) -> SmtpClient {
+        self.hello_name = hello_name.to_string();
+        self
+    }
+
+    /// Builds the SMTP transport
+    pub fn transport(self) -> SmtpTransport {
+        SmtpTransport {
+            client: Client::new(),
+            server_addr: self.server_addr,
+            hello_name: self.hello_name,
+            security: self.security,
+        }
+    }
+}
+
+/// Sends emails over one SMTP connection
+pub struct SmtpTransport {
+    client: Client,
+    server_addr: String,
+    hello_name: String,
+    security: ClientSecurity,
+}
+
+impl SmtpTransport {
+    fn connect(&mut self) -> Result<(), Error> {
+        let tls_parameters = match self.security {
+            ClientSecurity::None
+            | ClientSecurity::Opportunistic(_)
+            | ClientSecurity::Required(_) => None,
+            ClientSecurity::Wrapper(ref params) => Some(params),
+        };
+        self.client.connect(&self.server_addr as &str, tls_parameters)?;
+        self.client.read_response()?;
+        let mut ehlo_reply = self.ehlo()?;
+
+        match self.security {
+            ClientSecurity::Opportunistic(ref params) => {
+                if ehlo_reply.text.to_uppercase().contains("STARTTLS") {
+                    self.client.command("STARTTLS")?;
+                    self.client.read_response()?;
+                    self.client.upgrade_tls(params)?;
+                    // The server may advertise different capabilities once
+                    // encrypted, so the EHLO has to be sent again.
+                    ehlo_reply = self.ehlo()?;
+                }
+            }
+            ClientSecurity::Required(ref params) => {
+                if !ehlo_reply.text.to_uppercase().contains("STARTTLS") {
+                    return Err(Error::StartTlsRequired);
+                }
+                self.client.command("STARTTLS")?;
+                self.client.read_response()?;
+                self.client.upgrade_tls(params)?;
+                ehlo_reply = self.ehlo()?;
+            }
+            ClientSecurity::None | ClientSecurity::Wrapper(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Sends `EHLO` and reads back the (possibly multi-line) capability reply
+    fn ehlo(&mut self) -> Result<Reply, Error> {
+        self.client
+            .command(format!("EHLO {}", self.hello_name))?;
+        self.client.read_response()
+    }
+
+    fn send_envelope(&mut self, envelope: &Envelope) -> Result<(), Error> {
+        let from = envelope
+            .from()
+            .map(|a| a.to_string())
+            .unwrap_or_default();
+        self.client.command(format!("MAIL FROM:<{}>", from))?;
+        self.client.read_response()?;
+        for to in envelope.to() {
+            self.client.command(format!("RCPT TO:<{}>", to))?;
+            self.client.read_response()?;
+        }
+        self.client.command("DATA")?;
+        self.client.read_response()?;
+        Ok(())
+    }
+}
+
+impl<'a> Transport<'a> for SmtpTransport {
+    type Result = Result<(), Error>;
+
+    fn send(&mut self, email: SendableEmail) -> Self::Result {
+        self.connect()?;
+        let envelope = email.envelope().clone();
+        self.send_envelope(&envelope)?;
+        let mut message = email.message();
+        self.client.send_message(&mut message)?;
+        self.client.read_response()?;
+        self.client.command("QUIT")?;
+        self.client.read_response()?;
+        self.client.close();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smtp::client::net::ClientTlsParameters;
+
+    fn tls_parameters() -> ClientTlsParameters {
+        let connector = ::native_tls::TlsConnector::builder().build().unwrap();
+        ClientTlsParameters::new("example.com".to_string(), connector)
+    }
+
+    #[test]
+    fn default_port_is_25_for_plaintext() {
+        assert_eq!(ClientSecurity::None.default_port(), 25);
+    }
+
+    #[test]
+    fn default_port_is_587_for_starttls() {
+        assert_eq!(
+            ClientSecurity::Opportunistic(tls_parameters()).default_port(),
+            587
+        );
+        assert_eq!(
+            ClientSecurity::Required(tls_parameters()).default_port(),
+            587
+        );
+    }
+
+    #[test]
+    fn default_port_is_465_for_implicit_tls() {
+        assert_eq!(ClientSecurity::Wrapper(tls_parameters()).default_port(), 465);
+    }
+}